@@ -1,7 +1,13 @@
 use png::HasParameters;
 use std::fs::File;
-use std::io::BufWriter;
-use uom::si::{f64::*, length::meter};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub mod loader;
+pub mod material;
+pub mod math;
+pub mod scene;
+pub mod surfaces;
 
 #[derive(Copy, Clone)]
 /// A pixel containing RGBA data in floating point format. Values range from 0
@@ -18,11 +24,99 @@ pub struct Pixel {
     pub a: f64,
 }
 
+/// A reconstruction filter, controlling how a sample splatted at a
+/// continuous image position spreads its weight onto the surrounding
+/// pixels.
+#[derive(Copy, Clone)]
+pub enum Filter {
+    /// Every pixel within `radius` gets the full sample weight.
+    Box { radius: f64 },
+    /// Weight falls off linearly to `0` at `radius`.
+    Triangle { radius: f64 },
+    /// Weight falls off as `exp(-alpha * distance^2)`, within `radius`.
+    Gaussian { radius: f64, alpha: f64 },
+}
+
+impl Filter {
+    fn radius(self) -> f64 {
+        match self {
+            Filter::Box { radius } => radius,
+            Filter::Triangle { radius } => radius,
+            Filter::Gaussian { radius, .. } => radius,
+        }
+    }
+
+    /// The weight this filter assigns to a point `(dx, dy)` away from the
+    /// sample position, or `0.0` outside of the filter's radius.
+    fn weight(self, dx: f64, dy: f64) -> f64 {
+        let distance2 = dx * dx + dy * dy;
+        let radius = self.radius();
+        if distance2 > radius * radius {
+            return 0.0;
+        }
+
+        match self {
+            Filter::Box { .. } => 1.0,
+            Filter::Triangle { radius } => (1.0 - distance2.sqrt() / radius).max(0.0),
+            Filter::Gaussian { alpha, .. } => (-alpha * distance2).exp(),
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+/// A rectangular sub-region of an image, in pixel coordinates.
+#[derive(Copy, Clone)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    /// Make a new rectangle with top-left corner `(x, y)` and the given
+    /// size.
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// This rectangle, clamped so it lies entirely within an image of size
+    /// `image_width` by `image_height`.
+    fn clamped(self, image_width: usize, image_height: usize) -> Self {
+        let x = self.x.min(image_width);
+        let y = self.y.min(image_height);
+        let width = self.width.min(image_width.saturating_sub(x));
+        let height = self.height.min(image_height.saturating_sub(y));
+
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
 /// An image containing `Pixel`s.
 pub struct Image {
     width: usize,
     height: usize,
     pixels: Vec<Pixel>,
+    filter: Filter,
+    /// Weighted sum of samples splatted onto each pixel, as `(r, g, b)`.
+    sample_sums: Vec<(f64, f64, f64)>,
+    /// Total filter weight splatted onto each pixel.
+    sample_weights: Vec<f64>,
 }
 
 impl Pixel {
@@ -57,9 +151,122 @@ impl Image {
             width,
             height,
             pixels,
+            filter: Filter::default(),
+            sample_sums: vec![(0.0, 0.0, 0.0); width * height],
+            sample_weights: vec![0.0; width * height],
         }
     }
 
+    /// Use `filter` to reconstruct pixels from samples added with
+    /// `add_sample`, instead of the default box filter.
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    /// Splat a sample of color `pixel` at continuous image coordinates
+    /// `(x, y)`, weighted by `weight` and spread onto every pixel within the
+    /// image's filter radius according to that filter. Call `finalize` once
+    /// all samples have been added to turn the accumulated samples into
+    /// pixels.
+    pub fn add_sample(&mut self, x: f64, y: f64, pixel: Pixel, weight: f64) {
+        let radius = self.filter.radius();
+
+        let min_x = ((x - radius).floor().max(0.0)) as usize;
+        let max_x = ((x + radius).ceil() as usize).min(self.width.saturating_sub(1));
+        let min_y = ((y - radius).floor().max(0.0)) as usize;
+        let max_y = ((y + radius).ceil() as usize).min(self.height.saturating_sub(1));
+
+        for py in min_y..=max_y {
+            for px in min_x..=max_x {
+                let dx = (px as f64 + 0.5) - x;
+                let dy = (py as f64 + 0.5) - y;
+                let filter_weight = self.filter.weight(dx, dy);
+                if filter_weight <= 0.0 {
+                    continue;
+                }
+
+                let sample_weight = filter_weight * weight;
+                let index = self.width * py + px;
+                let sum = &mut self.sample_sums[index];
+                sum.0 += pixel.r * sample_weight;
+                sum.1 += pixel.g * sample_weight;
+                sum.2 += pixel.b * sample_weight;
+                self.sample_weights[index] += sample_weight;
+            }
+        }
+    }
+
+    /// Turn samples accumulated with `add_sample` into pixels, dividing each
+    /// pixel's weighted color sum by its total weight. Pixels that never
+    /// received a sample are left untouched.
+    pub fn finalize(&mut self) {
+        for index in 0..self.pixels.len() {
+            let weight = self.sample_weights[index];
+            if weight <= 0.0 {
+                continue;
+            }
+
+            let sum = self.sample_sums[index];
+            self.pixels[index] = Pixel {
+                r: sum.0 / weight,
+                g: sum.1 / weight,
+                b: sum.2 / weight,
+                a: 1.0,
+            };
+        }
+    }
+
+    /// Iterate over every pixel in the image as `(x, y, &Pixel)`.
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize, &Pixel)> {
+        let width = self.width;
+        self.pixels
+            .iter()
+            .enumerate()
+            .map(move |(index, pixel)| (index % width, index / width, pixel))
+    }
+
+    /// Like `pixels`, but mutable.
+    pub fn pixels_mut(&mut self) -> impl Iterator<Item = (usize, usize, &mut Pixel)> {
+        let width = self.width;
+        self.pixels
+            .iter_mut()
+            .enumerate()
+            .map(move |(index, pixel)| (index % width, index / width, pixel))
+    }
+
+    /// Iterate over the pixels within `rect` as `(x, y, &Pixel)`. `rect` is
+    /// clamped to the image bounds.
+    pub fn pixels_within<'a>(
+        &'a self,
+        rect: Rect,
+    ) -> impl Iterator<Item = (usize, usize, &'a Pixel)> + 'a {
+        let rect = rect.clamped(self.width, self.height);
+        let width = self.width;
+        (rect.y..rect.y + rect.height).flat_map(move |y| {
+            (rect.x..rect.x + rect.width).map(move |x| (x, y, &self.pixels[width * y + x]))
+        })
+    }
+
+    /// Like `pixels_within`, but mutable.
+    pub fn pixels_within_mut<'a>(
+        &'a mut self,
+        rect: Rect,
+    ) -> impl Iterator<Item = (usize, usize, &'a mut Pixel)> + 'a {
+        let rect = rect.clamped(self.width, self.height);
+        let width = self.width;
+        self.pixels
+            .chunks_mut(width)
+            .enumerate()
+            .skip(rect.y)
+            .take(rect.height)
+            .flat_map(move |(y, row)| {
+                row[rect.x..rect.x + rect.width]
+                    .iter_mut()
+                    .enumerate()
+                    .map(move |(i, pixel)| (rect.x + i, y, pixel))
+            })
+    }
+
     /// Set pixel at coordinate (`x`, `y`).
     pub fn set_pixel(&mut self, x: usize, y: usize, pixel: Pixel) {
         assert!(x < self.width);
@@ -68,6 +275,11 @@ impl Image {
         self.pixels[self.width * y + x] = pixel;
     }
 
+    /// The `(width, height)` of the image, in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
     /// Convert the image to a vector of gamma corrected SRGB data.
     pub fn to_srgba_vector(&self) -> Vec<u8> {
         let mut srgba_data = Vec::with_capacity(self.width * self.height * 4);
@@ -100,6 +312,31 @@ impl Image {
         png_writer.write_image_data(pixel_data).unwrap();
     }
 
+    /// Save the image as a binary (P6) PPM file.
+    pub fn save_ppm(&self, filename: &str) {
+        let srgba_vector = self.to_srgba_vector();
+
+        let mut ppm_file = BufWriter::new(File::create(filename).unwrap());
+        ppm_file
+            .write_all(format!("P6\n{} {}\n255\n", self.width, self.height).as_bytes())
+            .unwrap();
+
+        // Drop the alpha channel; PPM only stores RGB.
+        for rgba in srgba_vector.chunks_exact(4) {
+            ppm_file.write_all(&rgba[0..3]).unwrap();
+        }
+    }
+
+    /// Save the image, picking the format from `filename`'s extension
+    /// (`.png` or `.ppm`).
+    pub fn save(&self, filename: &str) {
+        match Path::new(filename).extension().and_then(|ext| ext.to_str()) {
+            Some("png") => self.save_png(filename),
+            Some("ppm") => self.save_ppm(filename),
+            other => panic!("unsupported image extension: {:?}", other),
+        }
+    }
+
     /// Read a png file into a vector of SRGB data.
     pub fn read_png(filename: &str) -> Vec<u8> {
         let png_file = File::open(filename).unwrap();
@@ -126,47 +363,6 @@ impl Image {
         (srgb * 255.0).round() as u8
     }
 
-    pub fn render_sphere(&mut self) {
-        // let aspect_ratio = window_width as f64 / window_height as f64;
-
-        let screen_width = Length::new::<meter>(0.64);
-
-        // We assume square pixels.
-        // let screen_height = screen_width / aspect_ratio;
-
-        // Distance from the eye, assumed at the origin, to the middle of the
-        // screen. The screen is oriented along the z-axis.
-        let distance_to_screen = Length::new::<meter>(0.5);
-
-        let pixel_size = screen_width / self.width as f64;
-
-        let sphere_center_x = Length::new::<meter>(0.0);
-        let sphere_center_y = Length::new::<meter>(0.0);
-        let sphere_center_z = Length::new::<meter>(5.0);
-        let sphere_radius = Length::new::<meter>(0.5);
-
-        for pixel_x in 0..self.width {
-            for pixel_y in 0..self.height {
-                let x = (pixel_x as f64 - 0.5 * (self.width - 1) as f64) * pixel_size;
-                let y = (pixel_y as f64 - 0.5 * (self.height - 1) as f64) * pixel_size;
-                let z = distance_to_screen;
-
-                let t = sphere_center_x * x + sphere_center_y * y + sphere_center_z * z;
-                let t = t / (x * x + y * y + z * z);
-
-                let mut surface_fun = (x * t - sphere_center_x) * (x * t - sphere_center_x);
-                surface_fun += (y * t - sphere_center_y) * (y * t - sphere_center_y);
-                surface_fun += (z * t - sphere_center_z) * (z * t - sphere_center_z);
-                surface_fun -= sphere_radius * sphere_radius;
-
-                let mut pixel = Pixel::new();
-                if surface_fun.is_sign_negative() {
-                    pixel.r = 1.0;
-                }
-                self.set_pixel(pixel_x, pixel_y, pixel);
-            }
-        }
-    }
 }
 
 #[cfg(test)]
@@ -185,19 +381,4 @@ mod tests {
         let ref_image_data = Image::read_png(ref_filename);
         assert_eq!(image_data, ref_image_data);
     }
-
-    #[test]
-    fn render_sphere() {
-        let mut image = Image::new(1280, 720);
-        let filename = "test-data/test-data-out/test_render_sphere.png";
-        let ref_filename = "test-data/test_render_sphere_ref.png";
-
-        image.render_sphere();
-
-        image.save_png(filename);
-
-        let image_data = image.to_srgba_vector();
-        let ref_image_data = Image::read_png(ref_filename);
-        assert_eq!(image_data, ref_image_data);
-    }
 }
@@ -0,0 +1,134 @@
+//! Loading `Scene`s from a plain-text scene description, so a scene can be
+//! changed without recompiling the crate.
+//!
+//! The format is line-oriented and whitespace-separated. Blank lines and
+//! lines starting with `#` are ignored. Supported lines are:
+//!
+//! ```text
+//! camera eye_x eye_y eye_z look_x look_y look_z up_x up_y up_z vfov aspect_ratio
+//! background r g b
+//! sphere cx cy cz radius
+//! plane nx ny nz distance_from_origin
+//! triangle v0x v0y v0z v1x v1y v1z v2x v2y v2z
+//! ```
+//!
+//! Every primitive is given a default matte white material; the format has
+//! no syntax for materials yet.
+
+use crate::material::Lambertian;
+use crate::scene::{Camera, Projection, Scene};
+use crate::surfaces::{Plane, Sphere, Surface, Triangle};
+use crate::Pixel;
+use std::fs;
+
+/// The material every primitive loaded from a scene file gets, since the
+/// format doesn't describe materials.
+fn default_material() -> Box<Lambertian> {
+    Box::new(Lambertian::new(Pixel {
+        r: 0.8,
+        g: 0.8,
+        b: 0.8,
+        a: 1.0,
+    }))
+}
+
+fn parse_floats(fields: &[&str]) -> Vec<f64> {
+    fields
+        .iter()
+        .map(|field| {
+            field
+                .parse::<f64>()
+                .unwrap_or_else(|_| panic!("expected a number, found `{}`", field))
+        })
+        .collect()
+}
+
+/// The numbers after a directive's name, checked against the count that
+/// directive expects so a short or malformed line fails with a message
+/// naming the directive, rather than an index-out-of-bounds panic once the
+/// caller starts reading individual fields.
+fn directive_args<'a>(fields: &'a [&'a str], directive: &str, expected: usize) -> &'a [&'a str] {
+    let args = &fields[1..];
+    assert!(
+        args.len() == expected,
+        "`{}` expects {} number(s), found {}",
+        directive,
+        expected,
+        args.len()
+    );
+    args
+}
+
+/// Load a `Scene` from the text scene description in `filename`.
+pub fn load_scene(filename: &str) -> Scene {
+    let contents = fs::read_to_string(filename)
+        .unwrap_or_else(|err| panic!("couldn't read scene file `{}`: {}", filename, err));
+
+    let mut camera = None;
+    let mut background = Pixel {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    };
+    let mut surfaces: Vec<Box<dyn Surface>> = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields[0] {
+            "camera" => {
+                let f = parse_floats(directive_args(&fields, "camera", 11));
+                camera = Some(Camera::new(
+                    (f[0], f[1], f[2]),
+                    (f[3], f[4], f[5]),
+                    (f[6], f[7], f[8]),
+                    Projection::Perspective { fov: f[9] },
+                    f[10],
+                ));
+            }
+            "background" => {
+                let f = parse_floats(directive_args(&fields, "background", 3));
+                background = Pixel {
+                    r: f[0],
+                    g: f[1],
+                    b: f[2],
+                    a: 1.0,
+                };
+            }
+            "sphere" => {
+                let f = parse_floats(directive_args(&fields, "sphere", 4));
+                surfaces.push(Box::new(Sphere::new(
+                    (f[0], f[1], f[2]),
+                    f[3],
+                    default_material(),
+                )));
+            }
+            "plane" => {
+                let f = parse_floats(directive_args(&fields, "plane", 4));
+                surfaces.push(Box::new(Plane::new(
+                    (f[0], f[1], f[2]),
+                    f[3],
+                    default_material(),
+                )));
+            }
+            "triangle" => {
+                let f = parse_floats(directive_args(&fields, "triangle", 9));
+                surfaces.push(Box::new(Triangle::new(
+                    (f[0], f[1], f[2]),
+                    (f[3], f[4], f[5]),
+                    (f[6], f[7], f[8]),
+                    default_material(),
+                )));
+            }
+            other => panic!("unknown scene directive `{}`", other),
+        }
+    }
+
+    let camera = camera.expect("scene file must define a camera");
+    Scene::new(surfaces, background, camera)
+}
@@ -0,0 +1,93 @@
+//! Materials describing how surfaces scatter light.
+
+use crate::math::{Ray, Vector3};
+use crate::Pixel;
+use rand::Rng;
+
+/// A `Material` decides how an incoming ray scatters off a surface it hit.
+pub trait Material {
+    /// Given the incoming ray and the point and normal of the intersection,
+    /// return the scattered ray and its attenuation, or `None` if the ray is
+    /// absorbed.
+    fn scatter(&self, incoming: &Ray, hit_point: Vector3, normal: Vector3) -> Option<(Ray, Pixel)>;
+}
+
+/// How far a scattered ray's origin is nudged off the surface along the
+/// normal, so it doesn't immediately re-intersect the surface it just left
+/// at `t≈0` due to floating-point rounding at the hit point (shadow acne).
+const SCATTER_EPS: f64 = 1e-8;
+
+/// An orthonormal basis `(u, v, w)` built around `w`, used to turn samples
+/// drawn in a canonical hemisphere into ones around an arbitrary normal.
+fn orthonormal_basis(w: Vector3) -> (Vector3, Vector3, Vector3) {
+    let a = if w.x.abs() > 0.9 {
+        Vector3::new(0.0, 1.0, 0.0)
+    } else {
+        Vector3::new(1.0, 0.0, 0.0)
+    };
+    let v = w.cross(a).normalize();
+    let u = w.cross(v);
+    (u, v, w)
+}
+
+/// A perfectly diffuse material that scatters uniformly over the cosine
+/// distribution around the surface normal.
+pub struct Lambertian {
+    pub albedo: Pixel,
+}
+
+impl Lambertian {
+    pub fn new(albedo: Pixel) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Material for Lambertian {
+    fn scatter(&self, _incoming: &Ray, hit_point: Vector3, normal: Vector3) -> Option<(Ray, Pixel)> {
+        let (u, v, w) = orthonormal_basis(normal);
+
+        let mut rng = rand::thread_rng();
+        let r1: f64 = rng.gen();
+        let r2: f64 = rng.gen();
+        let phi = 2.0 * std::f64::consts::PI * r1;
+        let x = phi.cos() * r2.sqrt();
+        let y = phi.sin() * r2.sqrt();
+        let z = (1.0 - r2).sqrt();
+
+        let direction = u * x + v * y + w * z;
+        let scattered = Ray {
+            origin: hit_point + normal * SCATTER_EPS,
+            direction,
+        };
+        Some((scattered, self.albedo))
+    }
+}
+
+/// A perfect mirror.
+pub struct Metal {
+    pub albedo: Pixel,
+}
+
+impl Metal {
+    pub fn new(albedo: Pixel) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Material for Metal {
+    fn scatter(&self, incoming: &Ray, hit_point: Vector3, normal: Vector3) -> Option<(Ray, Pixel)> {
+        let d = incoming.direction.normalize();
+        let reflected = d - normal * (2.0 * d.dot(normal));
+
+        if reflected.dot(normal) > 0.0 {
+            let scattered = Ray {
+                origin: hit_point + normal * SCATTER_EPS,
+                direction: reflected,
+            };
+            Some((scattered, self.albedo))
+        } else {
+            // The reflection points back into the surface; absorb it.
+            None
+        }
+    }
+}
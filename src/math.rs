@@ -0,0 +1,178 @@
+//! Basic vector math and geometric primitives shared by the rest of the crate.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// A three-dimensional vector, used both as a point and as a direction.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vector3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Vector3 {
+    /// Make a new vector from its three components.
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+
+    /// A vector with all components set to `0.0`.
+    pub fn zero() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+
+    /// A vector with all components set to `1.0`.
+    pub fn ones() -> Self {
+        Self::new(1.0, 1.0, 1.0)
+    }
+
+    /// Dot product with `other`.
+    pub fn dot(self, other: Self) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// Cross product with `other`.
+    pub fn cross(self, other: Self) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    /// Squared Euclidean norm.
+    pub fn norm2(self) -> f64 {
+        self.dot(self)
+    }
+
+    /// Euclidean norm.
+    pub fn norm(self) -> f64 {
+        self.norm2().sqrt()
+    }
+
+    /// This vector scaled to unit length.
+    pub fn normalize(self) -> Self {
+        self * (1.0 / self.norm())
+    }
+
+    /// Component-wise minimum of `self` and `other`.
+    pub fn min(self, other: Self) -> Self {
+        Self::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+
+    /// Component-wise maximum of `self` and `other`.
+    pub fn max(self, other: Self) -> Self {
+        Self::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+
+    /// Get the component along `axis` (0 = x, 1 = y, 2 = z).
+    pub fn component(self, axis: usize) -> f64 {
+        match axis {
+            0 => self.x,
+            1 => self.y,
+            2 => self.z,
+            _ => panic!("axis must be 0, 1 or 2"),
+        }
+    }
+}
+
+impl From<(f64, f64, f64)> for Vector3 {
+    fn from(components: (f64, f64, f64)) -> Self {
+        Self::new(components.0, components.1, components.2)
+    }
+}
+
+impl Add for Vector3 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Vector3 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Neg for Vector3 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mul<f64> for Vector3 {
+    type Output = Self;
+
+    fn mul(self, scalar: f64) -> Self {
+        Self::new(self.x * scalar, self.y * scalar, self.z * scalar)
+    }
+}
+
+impl Mul<Vector3> for f64 {
+    type Output = Vector3;
+
+    fn mul(self, vector: Vector3) -> Vector3 {
+        vector * self
+    }
+}
+
+/// A ray, defined by its `origin` and (not necessarily normalized) `direction`.
+#[derive(Copy, Clone, Debug)]
+pub struct Ray {
+    pub origin: Vector3,
+    pub direction: Vector3,
+}
+
+impl Ray {
+    /// Make a new ray from `origin` towards `direction`.
+    pub fn new<T: Into<Vector3>>(origin: T, direction: T) -> Self {
+        Self {
+            origin: origin.into(),
+            direction: direction.into(),
+        }
+    }
+
+    /// The point reached by travelling a distance `t` along the ray.
+    pub fn at(&self, t: f64) -> Vector3 {
+        self.origin + self.direction * t
+    }
+}
+
+/// A closed interval `[low, high]` on the real line.
+#[derive(Copy, Clone, Debug)]
+pub struct Interval {
+    low: f64,
+    high: f64,
+}
+
+impl Interval {
+    /// Make a new interval from two bounds, which may be given in either order.
+    pub fn new(a: f64, b: f64) -> Self {
+        Self {
+            low: a.min(b),
+            high: a.max(b),
+        }
+    }
+
+    /// The intersection of `self` and `other`, or `None` if they don't overlap.
+    pub fn intersection(&self, other: Self) -> Option<Self> {
+        let low = self.low.max(other.low);
+        let high = self.high.min(other.high);
+        if low > high {
+            None
+        } else {
+            Some(Self { low, high })
+        }
+    }
+
+    /// The `(low, high)` endpoints of the interval.
+    pub fn get_endpoints(&self) -> (f64, f64) {
+        (self.low, self.high)
+    }
+}
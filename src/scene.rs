@@ -0,0 +1,256 @@
+//! Scene description and pluggable rendering strategies.
+
+use crate::material::Material;
+use crate::math::{Ray, Vector3};
+use crate::surfaces::{Bvh, Hit, Surface};
+use crate::{Image, Pixel};
+use rand::Rng;
+
+/// How a `Camera` turns pixel positions into rays.
+pub enum Projection {
+    /// All rays share the `eye` origin and diverge through the image plane,
+    /// giving the usual pinhole-camera perspective. `fov` is the vertical
+    /// field of view, in degrees.
+    Perspective { fov: f64 },
+    /// Rays share a constant direction and each gets its own origin on the
+    /// image plane, giving a parallel (no-vanishing-point) projection.
+    /// `viewport_width` is the width of the image plane, in scene units.
+    Orthographic { viewport_width: f64 },
+}
+
+/// A camera that generates primary rays for an image of a given size.
+pub struct Camera {
+    eye: Vector3,
+    forward: Vector3,
+    right: Vector3,
+    up: Vector3,
+    projection: Projection,
+    /// Half the height of the image plane.
+    half_height: f64,
+    /// Half the width of the image plane.
+    half_width: f64,
+}
+
+impl Camera {
+    /// Make a camera at `eye`, looking towards `look_at`, with `up` giving
+    /// the roll around that direction, using `projection` to turn pixel
+    /// positions into rays. `aspect_ratio` is `width / height` of the image
+    /// that will be rendered through it.
+    pub fn new<T: Into<Vector3>>(
+        eye: T,
+        look_at: T,
+        up: T,
+        projection: Projection,
+        aspect_ratio: f64,
+    ) -> Self {
+        let eye = eye.into();
+        let forward = (look_at.into() - eye).normalize();
+        let right = forward.cross(up.into()).normalize();
+        let up = right.cross(forward);
+
+        let half_width = match projection {
+            Projection::Perspective { fov } => (fov.to_radians() / 2.0).tan() * aspect_ratio,
+            Projection::Orthographic { viewport_width } => viewport_width / 2.0,
+        };
+        let half_height = half_width / aspect_ratio;
+
+        Self {
+            eye,
+            forward,
+            right,
+            up,
+            projection,
+            half_height,
+            half_width,
+        }
+    }
+
+    /// The primary ray through continuous pixel coordinates `(px, py)` of an
+    /// image of size `width` by `height`. `px`/`py` need not be integers,
+    /// which lets renderers jitter samples within a pixel for antialiasing.
+    pub fn ray_for_pixel(&self, px: f64, py: f64, width: usize, height: usize) -> Ray {
+        let u = (px - width as f64 / 2.0) / (width as f64 / 2.0) * self.half_width;
+        let v = (height as f64 / 2.0 - py) / (height as f64 / 2.0) * self.half_height;
+
+        match self.projection {
+            Projection::Perspective { .. } => Ray {
+                origin: self.eye,
+                direction: (self.forward + self.right * u + self.up * v).normalize(),
+            },
+            Projection::Orthographic { .. } => Ray {
+                origin: self.eye + self.right * u + self.up * v,
+                direction: self.forward,
+            },
+        }
+    }
+}
+
+/// A collection of surfaces together with the camera and background used to
+/// render them.
+pub struct Scene {
+    surfaces: Bvh,
+    pub background: Pixel,
+    pub camera: Camera,
+}
+
+impl Scene {
+    /// Build a scene from its surfaces, background color and camera. The
+    /// surfaces are indexed into a `Bvh` for fast ray queries.
+    pub fn new(surfaces: Vec<Box<dyn Surface>>, background: Pixel, camera: Camera) -> Self {
+        Self {
+            surfaces: Bvh::new(surfaces),
+            background,
+            camera,
+        }
+    }
+
+    /// Find the closest intersection of `ray` with any surface in the scene.
+    pub fn closest_intersection(&self, ray: &Ray) -> Option<Hit> {
+        self.surfaces.closest_intersection(ray)
+    }
+}
+
+/// A `Renderer` turns a `Scene` into pixel data written into an `Image`.
+pub trait Renderer {
+    /// Render `scene` into `image`, which is assumed to already have the
+    /// size the renderer should produce.
+    fn render(&self, scene: &Scene, image: &mut Image);
+}
+
+/// The simplest possible renderer: colors each pixel by the geometric normal
+/// of the closest surface hit, or the scene background on a miss.
+pub struct FlatRenderer;
+
+impl FlatRenderer {
+    /// Map a unit normal to a color, so that surfaces facing the camera read
+    /// as distinct flat-shaded colors.
+    fn shade(normal: Vector3) -> Pixel {
+        Pixel {
+            r: 0.5 * (normal.x + 1.0),
+            g: 0.5 * (normal.y + 1.0),
+            b: 0.5 * (normal.z + 1.0),
+            a: 1.0,
+        }
+    }
+}
+
+impl Renderer for FlatRenderer {
+    fn render(&self, scene: &Scene, image: &mut Image) {
+        let (width, height) = image.size();
+
+        for y in 0..height {
+            for x in 0..width {
+                let ray = scene
+                    .camera
+                    .ray_for_pixel(x as f64 + 0.5, y as f64 + 0.5, width, height);
+
+                let pixel = match scene.closest_intersection(&ray) {
+                    Some(hit) => FlatRenderer::shade(hit.normal),
+                    None => scene.background,
+                };
+
+                image.set_pixel(x, y, pixel);
+            }
+        }
+    }
+}
+
+/// A path-tracing renderer: shoots `samples_per_pixel` jittered rays through
+/// each pixel and averages the linear-space results, following material
+/// scattering up to `max_depth` bounces.
+pub struct PathTracer {
+    pub samples_per_pixel: usize,
+    pub max_depth: usize,
+}
+
+impl PathTracer {
+    pub fn new(samples_per_pixel: usize, max_depth: usize) -> Self {
+        Self {
+            samples_per_pixel,
+            max_depth,
+        }
+    }
+
+    /// Trace a single ray through the scene, recursing into scattered rays up
+    /// to `depth` bounces and falling back to the scene background on a
+    /// miss or once the depth limit is reached.
+    fn trace(&self, scene: &Scene, ray: &Ray, depth: usize) -> Pixel {
+        if depth == 0 {
+            return Pixel {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+                a: 1.0,
+            };
+        }
+
+        let hit = match scene.closest_intersection(ray) {
+            Some(hit) => hit,
+            None => return scene.background,
+        };
+
+        let hit_point = ray.at(hit.distance);
+        match hit.material.scatter(ray, hit_point, hit.normal) {
+            Some((scattered, attenuation)) => {
+                let incoming = self.trace(scene, &scattered, depth - 1);
+                Pixel {
+                    r: attenuation.r * incoming.r,
+                    g: attenuation.g * incoming.g,
+                    b: attenuation.b * incoming.b,
+                    a: 1.0,
+                }
+            }
+            None => Pixel {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+                a: 1.0,
+            },
+        }
+    }
+}
+
+impl Renderer for PathTracer {
+    fn render(&self, scene: &Scene, image: &mut Image) {
+        let (width, height) = image.size();
+        let mut rng = rand::thread_rng();
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut accumulated = Pixel {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 1.0,
+                };
+
+                for _ in 0..self.samples_per_pixel {
+                    let jitter_x: f64 = rng.gen();
+                    let jitter_y: f64 = rng.gen();
+                    let ray = scene.camera.ray_for_pixel(
+                        x as f64 + jitter_x,
+                        y as f64 + jitter_y,
+                        width,
+                        height,
+                    );
+                    let sample = self.trace(scene, &ray, self.max_depth);
+                    accumulated.r += sample.r;
+                    accumulated.g += sample.g;
+                    accumulated.b += sample.b;
+                }
+
+                let sample_count = self.samples_per_pixel as f64;
+                image.set_pixel(
+                    x,
+                    y,
+                    Pixel {
+                        r: accumulated.r / sample_count,
+                        g: accumulated.g / sample_count,
+                        b: accumulated.b / sample_count,
+                        a: 1.0,
+                    },
+                );
+            }
+        }
+    }
+}
@@ -1,9 +1,11 @@
 //! Module containing the different surfaces that can be rendered.
 
+use crate::material::Material;
 use crate::math::{Interval, Ray, Vector3};
 use std::f64::{INFINITY, NEG_INFINITY};
 
-struct BoundingBox {
+#[derive(Clone, Copy)]
+pub struct BoundingBox {
     /// The first corner is the corner that has the lowest coordinate values,
     /// and the second, the highest coordinate values.
     corners: (Vector3, Vector3),
@@ -11,14 +13,51 @@ struct BoundingBox {
 
 impl BoundingBox {
     /// The two corners must be in opposite corners of the bounding box.
-    fn new<T: Into<Vector3>>(first_corner: T, second_corner: T) -> Self {
+    pub fn new<T: Into<Vector3>>(first_corner: T, second_corner: T) -> Self {
         Self {
             corners: (first_corner.into(), second_corner.into()),
         }
     }
 
+    /// The smallest bounding box containing both `self` and `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            corners: (
+                self.corners.0.min(other.corners.0),
+                self.corners.1.max(other.corners.1),
+            ),
+        }
+    }
+
+    /// A bounding box with no finite extent, for surfaces that aren't
+    /// actually bounded (e.g. an infinite `Plane`).
+    fn infinite() -> Self {
+        let corner = NEG_INFINITY * Vector3::ones();
+        Self::new(corner, -corner)
+    }
+
+    /// The point at the center of the bounding box.
+    ///
+    /// Only meaningful for a box with finite extent: an infinite corner on
+    /// one side and its negation on the other averages to `NaN`.
+    fn centroid(&self) -> Vector3 {
+        (self.corners.0 + self.corners.1) * 0.5
+    }
+
+    /// Does this box have finite extent? `false` for surfaces like `Plane`
+    /// that extend infinitely, which can't usefully be split on a centroid.
+    fn is_finite(&self) -> bool {
+        let (lo, hi) = self.corners;
+        lo.x.is_finite()
+            && lo.y.is_finite()
+            && lo.z.is_finite()
+            && hi.x.is_finite()
+            && hi.y.is_finite()
+            && hi.z.is_finite()
+    }
+
     /// Does the ray intersect the bounding box?
-    fn intersects(&self, ray: &Ray) -> bool {
+    pub fn intersects(&self, ray: &Ray) -> bool {
         // We intersect the ray and the 3 cardinal direction slabs generated
         // from the bounding box.
         let mut t_interval = if ray.direction.x != 0.0 {
@@ -58,30 +97,45 @@ impl BoundingBox {
     }
 }
 
+/// The result of a ray hitting a surface: how far along the ray, the
+/// geometric normal there, and the material to shade it with.
+pub struct Hit<'a> {
+    pub distance: f64,
+    pub normal: Vector3,
+    pub material: &'a dyn Material,
+}
+
 /// A `Surface` can intersect a `Ray`.
 pub trait Surface {
-    /// Find the length along a ray to the first intersection between the ray
-    /// and the surface (if any). Also returns the normal of the surface in the
-    /// intersection.
-    fn closest_intersection(&self, ray: &Ray) -> Option<(f64, Vector3)>;
+    /// Find the closest intersection between `ray` and the surface, if any.
+    fn closest_intersection(&self, ray: &Ray) -> Option<Hit>;
+
+    /// The smallest axis-aligned box containing the whole surface.
+    fn bounding_box(&self) -> BoundingBox;
 }
 
 pub struct Plane {
     normal_vec: Vector3,
     distance_from_origin: f64,
+    material: Box<dyn Material>,
 }
 
 impl Plane {
-    pub fn new<T: Into<Vector3>>(normal_vec: T, distance_from_origin: f64) -> Self {
+    pub fn new<T: Into<Vector3>>(
+        normal_vec: T,
+        distance_from_origin: f64,
+        material: Box<dyn Material>,
+    ) -> Self {
         Self {
             normal_vec: normal_vec.into().normalize(),
             distance_from_origin,
+            material,
         }
     }
 }
 
 impl Surface for Plane {
-    fn closest_intersection(&self, ray: &Ray) -> Option<(f64, Vector3)> {
+    fn closest_intersection(&self, ray: &Ray) -> Option<Hit> {
         let ray_direction_dot_normal = ray.direction.dot(self.normal_vec);
         if ray_direction_dot_normal == 0.0 {
             None
@@ -91,38 +145,43 @@ impl Surface for Plane {
                 - ray.origin.dot(self.normal_vec))
                 / ray_direction_dot_normal;
             if distance_to_intersection > 0.0 {
-                Some((distance_to_intersection, self.normal_vec))
+                Some(Hit {
+                    distance: distance_to_intersection,
+                    normal: self.normal_vec,
+                    material: self.material.as_ref(),
+                })
             } else {
                 None
             }
         }
     }
+
+    fn bounding_box(&self) -> BoundingBox {
+        // A plane extends infinitely, so its bounding box does too.
+        BoundingBox::infinite()
+    }
 }
 
 pub struct Sphere {
     pub center_pos: Vector3,
     /// In meters.
     pub radius: f64,
+    material: Box<dyn Material>,
 }
 
 impl Sphere {
     /// Make a sphere with center `center_pos` and radius `radius`.
-    pub fn new<T: Into<Vector3>>(center_pos: T, radius: f64) -> Self {
+    pub fn new<T: Into<Vector3>>(center_pos: T, radius: f64, material: Box<dyn Material>) -> Self {
         Self {
             center_pos: center_pos.into(),
             radius,
+            material,
         }
     }
-
-    /// Compute the minimal bounding box of the sphere.
-    fn bounding_box(&self) -> BoundingBox {
-        let radius_vec = self.radius * Vector3::ones();
-        BoundingBox::new(self.center_pos - radius_vec, self.center_pos + radius_vec)
-    }
 }
 
 impl Surface for Sphere {
-    fn closest_intersection(&self, ray: &Ray) -> Option<(f64, Vector3)> {
+    fn closest_intersection(&self, ray: &Ray) -> Option<Hit> {
         if self.bounding_box().intersects(ray) {
             let origin_to_center = self.center_pos - ray.origin;
             let origin_to_center_dot_dir = origin_to_center.dot(ray.direction);
@@ -142,11 +201,361 @@ impl Surface for Sphere {
                 }
                 let normal =
                     (ray.direction * distance_to_intersection - origin_to_center).normalize();
-                Some((distance_to_intersection, normal))
+                Some(Hit {
+                    distance: distance_to_intersection,
+                    normal,
+                    material: self.material.as_ref(),
+                })
             }
         } else {
             // Ray doesn't intersect bounding box.
             None
         }
     }
+
+    fn bounding_box(&self) -> BoundingBox {
+        let radius_vec = self.radius * Vector3::ones();
+        BoundingBox::new(self.center_pos - radius_vec, self.center_pos + radius_vec)
+    }
+}
+
+/// The smallest positive distance along a ray before an intersection is
+/// considered to actually have hit something, rather than be a rounding
+/// artifact of the surface it started from.
+const INTERSECTION_EPS: f64 = 1e-8;
+
+/// A triangle, defined by its three vertices in counter-clockwise order (as
+/// seen from the side the normal points to).
+pub struct Triangle {
+    pub v0: Vector3,
+    pub v1: Vector3,
+    pub v2: Vector3,
+    material: Box<dyn Material>,
+}
+
+impl Triangle {
+    /// Make a new triangle from its three vertices.
+    pub fn new<T: Into<Vector3>>(v0: T, v1: T, v2: T, material: Box<dyn Material>) -> Self {
+        Self {
+            v0: v0.into(),
+            v1: v1.into(),
+            v2: v2.into(),
+            material,
+        }
+    }
+
+    /// Möller–Trumbore ray/triangle intersection. Returns the distance along
+    /// the ray to the intersection and the geometric normal, oriented against
+    /// the ray direction.
+    fn intersect(v0: Vector3, v1: Vector3, v2: Vector3, ray: &Ray) -> Option<(f64, Vector3)> {
+        let e1 = v1 - v0;
+        let e2 = v2 - v0;
+
+        let p = ray.direction.cross(e2);
+        let det = e1.dot(p);
+        if det.abs() < INTERSECTION_EPS {
+            // Ray is (nearly) parallel to the triangle.
+            return None;
+        }
+        let inv = 1.0 / det;
+
+        let tvec = ray.origin - v0;
+        let u = tvec.dot(p) * inv;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = tvec.cross(e1);
+        let v = ray.direction.dot(q) * inv;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = e2.dot(q) * inv;
+        if t <= INTERSECTION_EPS {
+            return None;
+        }
+
+        let mut normal = e1.cross(e2).normalize();
+        if normal.dot(ray.direction) > 0.0 {
+            normal = -normal;
+        }
+        Some((t, normal))
+    }
+}
+
+impl Surface for Triangle {
+    fn closest_intersection(&self, ray: &Ray) -> Option<Hit> {
+        Triangle::intersect(self.v0, self.v1, self.v2, ray).map(|(distance, normal)| Hit {
+            distance,
+            normal,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> BoundingBox {
+        let min = self.v0.min(self.v1).min(self.v2);
+        let max = self.v0.max(self.v1).max(self.v2);
+        BoundingBox::new(min, max)
+    }
+}
+
+/// A mesh of triangles sharing a single vertex buffer and material, so
+/// vertices used by several faces aren't duplicated in memory.
+pub struct TriangleMesh {
+    vertices: Vec<Vector3>,
+    /// Each entry is a triple of indices into `vertices` forming one triangle.
+    indices: Vec<(usize, usize, usize)>,
+    material: Box<dyn Material>,
+}
+
+impl TriangleMesh {
+    /// Make a new mesh from a vertex buffer and a list of index triples.
+    pub fn new(
+        vertices: Vec<Vector3>,
+        indices: Vec<(usize, usize, usize)>,
+        material: Box<dyn Material>,
+    ) -> Self {
+        Self {
+            vertices,
+            indices,
+            material,
+        }
+    }
+}
+
+impl Surface for TriangleMesh {
+    fn closest_intersection(&self, ray: &Ray) -> Option<Hit> {
+        let mut closest: Option<(f64, Vector3)> = None;
+
+        for &(i0, i1, i2) in self.indices.iter() {
+            let v0 = self.vertices[i0];
+            let v1 = self.vertices[i1];
+            let v2 = self.vertices[i2];
+
+            if let Some(hit) = Triangle::intersect(v0, v1, v2, ray) {
+                if closest.map_or(true, |(t, _)| hit.0 < t) {
+                    closest = Some(hit);
+                }
+            }
+        }
+
+        closest.map(|(distance, normal)| Hit {
+            distance,
+            normal,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounding_box(&self) -> BoundingBox {
+        let mut min = self.vertices[0];
+        let mut max = self.vertices[0];
+        for &vertex in self.vertices.iter().skip(1) {
+            min = min.min(vertex);
+            max = max.max(vertex);
+        }
+        BoundingBox::new(min, max)
+    }
+}
+
+/// Leaves are created once a node holds this few primitives or fewer.
+const BVH_LEAF_THRESHOLD: usize = 4;
+
+enum BvhNode {
+    Leaf {
+        bbox: BoundingBox,
+        range: (usize, usize),
+    },
+    Internal {
+        bbox: BoundingBox,
+        left: Box<BvhNode>,
+        right: Box<BvhNode>,
+    },
+}
+
+impl BvhNode {
+    /// Build a node covering `indices[range.0..range.1]`, reordering `indices`
+    /// in place along the way so that each node's primitives end up
+    /// contiguous.
+    fn build(indices: &mut [usize], range: (usize, usize), bboxes: &[BoundingBox]) -> Self {
+        let (start, end) = range;
+        let bbox = indices[start..end]
+            .iter()
+            .map(|&i| bboxes[i])
+            .fold(None, |acc: Option<BoundingBox>, b| {
+                Some(match acc {
+                    Some(acc) => acc.union(&b),
+                    None => b,
+                })
+            })
+            .expect("a BVH node must cover at least one primitive");
+
+        if end - start <= BVH_LEAF_THRESHOLD {
+            return BvhNode::Leaf { bbox, range };
+        }
+
+        // Split along the axis with the largest centroid extent.
+        let centroids: Vec<Vector3> = indices[start..end]
+            .iter()
+            .map(|&i| bboxes[i].centroid())
+            .collect();
+        let min_centroid = centroids
+            .iter()
+            .fold(centroids[0], |acc, &c| acc.min(c));
+        let max_centroid = centroids
+            .iter()
+            .fold(centroids[0], |acc, &c| acc.max(c));
+        let extent = max_centroid - min_centroid;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+
+        indices[start..end]
+            .sort_by(|&a, &b| {
+                bboxes[a]
+                    .centroid()
+                    .component(axis)
+                    .partial_cmp(&bboxes[b].centroid().component(axis))
+                    .unwrap()
+            });
+
+        let mid = start + (end - start) / 2;
+        let left = BvhNode::build(indices, (start, mid), bboxes);
+        let right = BvhNode::build(indices, (mid, end), bboxes);
+
+        BvhNode::Internal {
+            bbox,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    fn closest_intersection<'a>(
+        &self,
+        surfaces: &'a [Box<dyn Surface>],
+        ray: &Ray,
+    ) -> Option<Hit<'a>> {
+        let bbox = match self {
+            BvhNode::Leaf { bbox, .. } => bbox,
+            BvhNode::Internal { bbox, .. } => bbox,
+        };
+        if !bbox.intersects(ray) {
+            return None;
+        }
+
+        match self {
+            BvhNode::Leaf { range, .. } => surfaces[range.0..range.1]
+                .iter()
+                .filter_map(|surface| surface.closest_intersection(ray))
+                .fold(None, |closest: Option<Hit>, hit| match closest {
+                    Some(ref closest_hit) if closest_hit.distance <= hit.distance => closest,
+                    _ => Some(hit),
+                }),
+            BvhNode::Internal { left, right, .. } => {
+                let left_hit = left.closest_intersection(surfaces, ray);
+                let right_hit = right.closest_intersection(surfaces, ray);
+                match (left_hit, right_hit) {
+                    (Some(l), Some(r)) => Some(if l.distance <= r.distance { l } else { r }),
+                    (Some(l), None) => Some(l),
+                    (None, Some(r)) => Some(r),
+                    (None, None) => None,
+                }
+            }
+        }
+    }
+}
+
+/// A bounding volume hierarchy, accelerating `closest_intersection` queries
+/// over a collection of surfaces from `O(n)` to roughly `O(log n)`.
+///
+/// Surfaces with an infinite bounding box (e.g. a `Plane`) have no
+/// meaningful centroid to split on, and including one in a node's bbox
+/// would make every ancestor's bbox infinite too, defeating the purpose of
+/// the hierarchy. They're instead kept out of the tree entirely, in
+/// `surfaces[unbounded_start..]`, and tested against every ray directly.
+pub struct Bvh {
+    surfaces: Vec<Box<dyn Surface>>,
+    /// `None` when there are no bounded surfaces to build a tree over.
+    root: Option<BvhNode>,
+    unbounded_start: usize,
+}
+
+impl Bvh {
+    /// Build a BVH over `surfaces`. The surfaces are reordered internally to
+    /// keep each node's primitives contiguous.
+    pub fn new(surfaces: Vec<Box<dyn Surface>>) -> Self {
+        assert!(!surfaces.is_empty(), "a Bvh must cover at least one surface");
+
+        let bboxes: Vec<BoundingBox> = surfaces.iter().map(|s| s.bounding_box()).collect();
+
+        // Bounded surfaces sort before unbounded ones, so the tree can be
+        // built over just the bounded prefix.
+        let mut indices: Vec<usize> = (0..surfaces.len()).collect();
+        indices.sort_by_key(|&i| !bboxes[i].is_finite());
+        let unbounded_start = indices.partition_point(|&i| bboxes[i].is_finite());
+
+        let root = if unbounded_start > 0 {
+            Some(BvhNode::build(&mut indices, (0, unbounded_start), &bboxes))
+        } else {
+            None
+        };
+
+        let mut slots: Vec<Option<Box<dyn Surface>>> = surfaces.into_iter().map(Some).collect();
+        let surfaces = indices
+            .iter()
+            .map(|&i| slots[i].take().unwrap())
+            .collect();
+
+        Self {
+            surfaces,
+            root,
+            unbounded_start,
+        }
+    }
+}
+
+impl Surface for Bvh {
+    fn closest_intersection(&self, ray: &Ray) -> Option<Hit> {
+        let tree_hit = self
+            .root
+            .as_ref()
+            .and_then(|root| root.closest_intersection(&self.surfaces[..self.unbounded_start], ray));
+
+        let unbounded_hit = self.surfaces[self.unbounded_start..]
+            .iter()
+            .filter_map(|surface| surface.closest_intersection(ray))
+            .fold(None, |closest: Option<Hit>, hit| match closest {
+                Some(ref closest_hit) if closest_hit.distance <= hit.distance => closest,
+                _ => Some(hit),
+            });
+
+        match (tree_hit, unbounded_hit) {
+            (Some(a), Some(b)) => Some(if a.distance <= b.distance { a } else { b }),
+            (Some(a), None) => Some(a),
+            (None, Some(b)) => Some(b),
+            (None, None) => None,
+        }
+    }
+
+    fn bounding_box(&self) -> BoundingBox {
+        let tree_bbox = self.root.as_ref().map(|root| match root {
+            BvhNode::Leaf { bbox, .. } => *bbox,
+            BvhNode::Internal { bbox, .. } => *bbox,
+        });
+
+        if self.unbounded_start == self.surfaces.len() {
+            // No unbounded surfaces, so `Bvh::new`'s non-empty check guarantees a tree.
+            return tree_bbox.expect("unreachable: Bvh::new rejects an empty surface list");
+        }
+
+        // At least one surface is unbounded, so the whole Bvh is too.
+        match tree_bbox {
+            Some(bbox) => bbox.union(&BoundingBox::infinite()),
+            None => BoundingBox::infinite(),
+        }
+    }
 }